@@ -0,0 +1,165 @@
+use ::std::collections::{BTreeMap, VecDeque};
+use ::std::net::IpAddr;
+use ::std::time::{Duration, Instant};
+
+use crate::display::ui_state::UIState;
+
+static DEFAULT_RETENTION: Duration = Duration::from_secs(60);
+
+pub struct ThroughputSample {
+    pub at: Instant,
+    pub total_bytes_downloaded: u128,
+    pub total_bytes_uploaded: u128,
+}
+
+/// Bounded, time-indexed history of `UIState` snapshots. Unlike the flat
+/// `UIState` snapshot, this retains enough of the recent past to drive
+/// per-process/per-remote-address throughput sparklines.
+pub struct DataHistory {
+    retention: Duration,
+    totals: VecDeque<ThroughputSample>,
+    processes: BTreeMap<String, VecDeque<ThroughputSample>>,
+    remote_addresses: BTreeMap<IpAddr, VecDeque<ThroughputSample>>,
+}
+
+impl Default for DataHistory {
+    fn default() -> Self {
+        DataHistory::new(DEFAULT_RETENTION)
+    }
+}
+
+impl DataHistory {
+    pub fn new(retention: Duration) -> Self {
+        DataHistory {
+            retention,
+            totals: VecDeque::new(),
+            processes: BTreeMap::new(),
+            remote_addresses: BTreeMap::new(),
+        }
+    }
+
+    /// Appends the newest sample taken from `state` and evicts anything
+    /// older than `retention` from every series.
+    pub fn ingest(&mut self, state: &UIState) {
+        let now = Instant::now();
+        let retention = self.retention;
+
+        Self::push(
+            &mut self.totals,
+            now,
+            state.total_bytes_downloaded,
+            state.total_bytes_uploaded,
+            retention,
+        );
+
+        for (process_name, data) in &state.processes {
+            let series = self.processes.entry(process_name.clone()).or_default();
+            Self::push(
+                series,
+                now,
+                data.total_bytes_downloaded,
+                data.total_bytes_uploaded,
+                retention,
+            );
+        }
+
+        for (remote_address, data) in &state.remote_addresses {
+            let series = self.remote_addresses.entry(*remote_address).or_default();
+            Self::push(
+                series,
+                now,
+                data.total_bytes_downloaded,
+                data.total_bytes_uploaded,
+                retention,
+            );
+        }
+
+        // Entities that dropped out of `state` (a process exited, a remote
+        // host stopped appearing) stop receiving new samples, so their
+        // series would otherwise keep every sample forever. Age them out by
+        // the same retention window and drop the key once its series is
+        // empty, so the map doesn't grow without bound.
+        self.processes.retain(|process_name, series| {
+            if !state.processes.contains_key(process_name) {
+                Self::prune_stale(series, now, retention);
+            }
+            !series.is_empty()
+        });
+        self.remote_addresses.retain(|remote_address, series| {
+            if !state.remote_addresses.contains_key(remote_address) {
+                Self::prune_stale(series, now, retention);
+            }
+            !series.is_empty()
+        });
+    }
+
+    pub fn totals(&self) -> &VecDeque<ThroughputSample> {
+        &self.totals
+    }
+
+    pub fn process_series(&self, process_name: &str) -> Option<&VecDeque<ThroughputSample>> {
+        self.processes.get(process_name)
+    }
+
+    pub fn remote_address_series(
+        &self,
+        remote_address: &IpAddr,
+    ) -> Option<&VecDeque<ThroughputSample>> {
+        self.remote_addresses.get(remote_address)
+    }
+
+    fn push(
+        series: &mut VecDeque<ThroughputSample>,
+        at: Instant,
+        total_bytes_downloaded: u128,
+        total_bytes_uploaded: u128,
+        retention: Duration,
+    ) {
+        series.push_back(ThroughputSample {
+            at,
+            total_bytes_downloaded,
+            total_bytes_uploaded,
+        });
+        Self::prune_stale(series, at, retention);
+    }
+
+    fn prune_stale(series: &mut VecDeque<ThroughputSample>, now: Instant, retention: Duration) {
+        while let Some(oldest) = series.front() {
+            if now.duration_since(oldest.at) > retention {
+                series.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::ui_state::NetworkData;
+    use ::std::thread;
+
+    #[test]
+    fn prunes_a_process_series_once_it_drops_out_of_state_and_ages_out() {
+        let mut history = DataHistory::new(Duration::from_millis(0));
+
+        let mut state = UIState::default();
+        state.processes.insert(
+            "proc".to_string(),
+            NetworkData {
+                total_bytes_downloaded: 1000,
+                total_bytes_uploaded: 0,
+                ..Default::default()
+            },
+        );
+        history.ingest(&state);
+        assert!(history.process_series("proc").is_some());
+
+        // "proc" no longer appears in state, e.g. the process exited.
+        thread::sleep(Duration::from_millis(5));
+        history.ingest(&UIState::default());
+
+        assert!(history.process_series("proc").is_none());
+    }
+}
@@ -1,9 +1,11 @@
 use ::std::collections::{BTreeMap, HashMap};
-use ::std::net::Ipv4Addr;
+use ::std::net::IpAddr;
 
 use crate::network::{Connection, Utilization};
 
 static BANDWIDTH_DECAY_FACTOR: f32 = 0.5;
+// Number of recent per-refresh samples kept per entity for peak tracking.
+static BANDWIDTH_TABLE_LENGTH: usize = 10;
 
 pub trait Bandwidth {
     fn get_total_bytes_downloaded(&self) -> u128;
@@ -11,6 +13,9 @@ pub trait Bandwidth {
 
     fn get_avg_bytes_downloaded(&self) -> u128;
     fn get_avg_bytes_uploaded(&self) -> u128;
+
+    fn get_max_bytes_downloaded(&self) -> u128;
+    fn get_max_bytes_uploaded(&self) -> u128;
 }
 
 #[derive(Default)]
@@ -20,6 +25,8 @@ pub struct NetworkData {
     pub prev_total_bytes_downloaded: u128,
     pub prev_total_bytes_uploaded: u128,
     pub connection_count: u128,
+    pub download_bandwidth_table: Vec<u128>,
+    pub upload_bandwidth_table: Vec<u128>,
 }
 
 #[derive(Default)]
@@ -30,6 +37,8 @@ pub struct ConnectionData {
     pub prev_total_bytes_uploaded: u128,
     pub process_name: String,
     pub interface_name: String,
+    pub download_bandwidth_table: Vec<u128>,
+    pub upload_bandwidth_table: Vec<u128>,
 }
 
 fn calc_avg_bandwidth(prev_bandwidth: u128, curr_bandwidth: u128) -> u128 {
@@ -41,6 +50,72 @@ fn calc_avg_bandwidth(prev_bandwidth: u128, curr_bandwidth: u128) -> u128 {
     }
 }
 
+fn calc_max_bandwidth(bandwidth_table: &[u128]) -> u128 {
+    bandwidth_table.iter().copied().max().unwrap_or(0)
+}
+
+/// Selects which of the per-entity aggregations `UIState::new` builds.
+/// Disabled views are skipped entirely, so callers that only render, say,
+/// the processes table don't pay for maintaining the connections and
+/// remote-addresses maps on every refresh.
+#[derive(Clone, Copy, Default)]
+pub struct RefreshKind {
+    processes: bool,
+    remote_addresses: bool,
+    connections: bool,
+}
+
+impl RefreshKind {
+    pub fn new() -> Self {
+        RefreshKind::default()
+    }
+
+    pub fn everything() -> Self {
+        RefreshKind {
+            processes: true,
+            remote_addresses: true,
+            connections: true,
+        }
+    }
+
+    pub fn with_processes(mut self) -> Self {
+        self.processes = true;
+        self
+    }
+
+    pub fn with_remote_addresses(mut self) -> Self {
+        self.remote_addresses = true;
+        self
+    }
+
+    pub fn with_connections(mut self) -> Self {
+        self.connections = true;
+        self
+    }
+
+    pub fn processes(&self) -> bool {
+        self.processes
+    }
+
+    pub fn remote_addresses(&self) -> bool {
+        self.remote_addresses
+    }
+
+    pub fn connections(&self) -> bool {
+        self.connections
+    }
+}
+
+/// Appends `sample` to `table`, evicting the oldest entry once the table is
+/// full, so each entity only ever keeps the last `BANDWIDTH_TABLE_LENGTH`
+/// per-refresh samples.
+fn record_bandwidth_sample(table: &mut Vec<u128>, sample: u128) {
+    if table.len() >= BANDWIDTH_TABLE_LENGTH {
+        table.remove(0);
+    }
+    table.push(sample);
+}
+
 impl Bandwidth for ConnectionData {
     fn get_total_bytes_uploaded(&self) -> u128 {
         self.total_bytes_uploaded
@@ -57,6 +132,12 @@ impl Bandwidth for ConnectionData {
             self.total_bytes_downloaded,
         )
     }
+    fn get_max_bytes_downloaded(&self) -> u128 {
+        calc_max_bandwidth(&self.download_bandwidth_table)
+    }
+    fn get_max_bytes_uploaded(&self) -> u128 {
+        calc_max_bandwidth(&self.upload_bandwidth_table)
+    }
 }
 
 impl Bandwidth for NetworkData {
@@ -75,12 +156,18 @@ impl Bandwidth for NetworkData {
             self.total_bytes_downloaded,
         )
     }
+    fn get_max_bytes_downloaded(&self) -> u128 {
+        calc_max_bandwidth(&self.download_bandwidth_table)
+    }
+    fn get_max_bytes_uploaded(&self) -> u128 {
+        calc_max_bandwidth(&self.upload_bandwidth_table)
+    }
 }
 
 #[derive(Default)]
 pub struct UIState {
     pub processes: BTreeMap<String, NetworkData>,
-    pub remote_addresses: BTreeMap<Ipv4Addr, NetworkData>,
+    pub remote_addresses: BTreeMap<IpAddr, NetworkData>,
     pub connections: BTreeMap<Connection, ConnectionData>,
     pub total_bytes_downloaded: u128,
     pub total_bytes_uploaded: u128,
@@ -91,52 +178,138 @@ impl UIState {
         connections_to_procs: HashMap<Connection, String>,
         mut network_utilization: Utilization,
         old_state: &UIState,
+        refresh_kind: RefreshKind,
     ) -> Self {
         let mut processes: BTreeMap<String, NetworkData> = BTreeMap::new();
-        let mut remote_addresses: BTreeMap<Ipv4Addr, NetworkData> = BTreeMap::new();
+        let mut remote_addresses: BTreeMap<IpAddr, NetworkData> = BTreeMap::new();
         let mut connections: BTreeMap<Connection, ConnectionData> = BTreeMap::new();
         let mut total_bytes_downloaded: u128 = 0;
         let mut total_bytes_uploaded: u128 = 0;
         for (connection, process_name) in connections_to_procs {
             if let Some(connection_info) = network_utilization.connections.remove(&connection) {
-                let data_for_remote_address = remote_addresses
-                    .entry(connection.remote_socket.ip)
-                    .or_default();
-                let connection_data = connections.entry(connection).or_default();
-                let data_for_process = processes.entry(process_name.clone()).or_default();
-
-                data_for_process.total_bytes_downloaded += connection_info.total_bytes_downloaded;
-                data_for_process.total_bytes_uploaded += connection_info.total_bytes_uploaded;
-                data_for_process.connection_count += 1;
-                connection_data.total_bytes_downloaded += connection_info.total_bytes_downloaded;
-                connection_data.total_bytes_uploaded += connection_info.total_bytes_uploaded;
-                connection_data.process_name = process_name;
-                connection_data.interface_name = connection_info.interface_name;
-                data_for_remote_address.total_bytes_downloaded +=
-                    connection_info.total_bytes_downloaded;
-                data_for_remote_address.total_bytes_uploaded +=
-                    connection_info.total_bytes_uploaded;
-                data_for_remote_address.connection_count += 1;
                 total_bytes_downloaded += connection_info.total_bytes_downloaded;
                 total_bytes_uploaded += connection_info.total_bytes_uploaded;
 
-                // Record bandwidth data of last iteration
-                if let Some(prev_connection_info) = old_state.connections.get(&connection) {
-                    // Using previous round's weighted average. Exponential decay
-                    let prev_bytes_downloaded = prev_connection_info.get_avg_bytes_downloaded();
-                    let prev_bytes_uploaded = prev_connection_info.get_avg_bytes_uploaded();
+                // Each scope seeds its own previous-round weighted average from
+                // its own `old_state` map, since that map is only populated when
+                // the matching `RefreshKind` flag is enabled.
+                if refresh_kind.remote_addresses() {
+                    let remote_ip = connection.remote_socket.ip;
+                    let prev_remote_address_info = old_state.remote_addresses.get(&remote_ip);
+                    let prev_bytes_downloaded = prev_remote_address_info
+                        .map(|data| data.get_avg_bytes_downloaded())
+                        .unwrap_or(0);
+                    let prev_bytes_uploaded = prev_remote_address_info
+                        .map(|data| data.get_avg_bytes_uploaded())
+                        .unwrap_or(0);
 
-                    connection_data.prev_total_bytes_downloaded += prev_bytes_downloaded;
-                    connection_data.prev_total_bytes_uploaded += prev_bytes_uploaded;
+                    let data_for_remote_address = remote_addresses.entry(remote_ip).or_default();
+                    data_for_remote_address.total_bytes_downloaded +=
+                        connection_info.total_bytes_downloaded;
+                    data_for_remote_address.total_bytes_uploaded +=
+                        connection_info.total_bytes_uploaded;
+                    data_for_remote_address.connection_count += 1;
+                    if prev_remote_address_info.is_some() {
+                        data_for_remote_address.prev_total_bytes_downloaded +=
+                            prev_bytes_downloaded;
+                        data_for_remote_address.prev_total_bytes_uploaded += prev_bytes_uploaded;
+                    }
+                }
 
-                    data_for_process.prev_total_bytes_downloaded += prev_bytes_downloaded;
-                    data_for_process.prev_total_bytes_uploaded += prev_bytes_uploaded;
+                if refresh_kind.connections() {
+                    let prev_connection_info = old_state.connections.get(&connection);
+                    let prev_bytes_downloaded = prev_connection_info
+                        .map(|data| data.get_avg_bytes_downloaded())
+                        .unwrap_or(0);
+                    let prev_bytes_uploaded = prev_connection_info
+                        .map(|data| data.get_avg_bytes_uploaded())
+                        .unwrap_or(0);
 
-                    data_for_remote_address.prev_total_bytes_downloaded += prev_bytes_downloaded;
-                    data_for_remote_address.prev_total_bytes_uploaded += prev_bytes_uploaded;
+                    let connection_data = connections.entry(connection).or_default();
+                    connection_data.total_bytes_downloaded +=
+                        connection_info.total_bytes_downloaded;
+                    connection_data.total_bytes_uploaded += connection_info.total_bytes_uploaded;
+                    connection_data.process_name = process_name.clone();
+                    connection_data.interface_name = connection_info.interface_name;
+                    if prev_connection_info.is_some() {
+                        connection_data.prev_total_bytes_downloaded += prev_bytes_downloaded;
+                        connection_data.prev_total_bytes_uploaded += prev_bytes_uploaded;
+                    }
+                }
+
+                if refresh_kind.processes() {
+                    let prev_process_info = old_state.processes.get(&process_name);
+                    let prev_bytes_downloaded = prev_process_info
+                        .map(|data| data.get_avg_bytes_downloaded())
+                        .unwrap_or(0);
+                    let prev_bytes_uploaded = prev_process_info
+                        .map(|data| data.get_avg_bytes_uploaded())
+                        .unwrap_or(0);
+
+                    let data_for_process = processes.entry(process_name).or_default();
+                    data_for_process.total_bytes_downloaded +=
+                        connection_info.total_bytes_downloaded;
+                    data_for_process.total_bytes_uploaded += connection_info.total_bytes_uploaded;
+                    data_for_process.connection_count += 1;
+                    if prev_process_info.is_some() {
+                        data_for_process.prev_total_bytes_downloaded += prev_bytes_downloaded;
+                        data_for_process.prev_total_bytes_uploaded += prev_bytes_uploaded;
+                    }
                 }
             }
         }
+        for (process_name, data_for_process) in processes.iter_mut() {
+            let prev_data = old_state.processes.get(process_name);
+            data_for_process.download_bandwidth_table = prev_data
+                .map(|data| data.download_bandwidth_table.clone())
+                .unwrap_or_default();
+            data_for_process.upload_bandwidth_table = prev_data
+                .map(|data| data.upload_bandwidth_table.clone())
+                .unwrap_or_default();
+            record_bandwidth_sample(
+                &mut data_for_process.download_bandwidth_table,
+                data_for_process.total_bytes_downloaded,
+            );
+            record_bandwidth_sample(
+                &mut data_for_process.upload_bandwidth_table,
+                data_for_process.total_bytes_uploaded,
+            );
+        }
+        for (remote_address, data_for_remote_address) in remote_addresses.iter_mut() {
+            let prev_data = old_state.remote_addresses.get(remote_address);
+            data_for_remote_address.download_bandwidth_table = prev_data
+                .map(|data| data.download_bandwidth_table.clone())
+                .unwrap_or_default();
+            data_for_remote_address.upload_bandwidth_table = prev_data
+                .map(|data| data.upload_bandwidth_table.clone())
+                .unwrap_or_default();
+            record_bandwidth_sample(
+                &mut data_for_remote_address.download_bandwidth_table,
+                data_for_remote_address.total_bytes_downloaded,
+            );
+            record_bandwidth_sample(
+                &mut data_for_remote_address.upload_bandwidth_table,
+                data_for_remote_address.total_bytes_uploaded,
+            );
+        }
+        for (connection, connection_data) in connections.iter_mut() {
+            let prev_data = old_state.connections.get(connection);
+            connection_data.download_bandwidth_table = prev_data
+                .map(|data| data.download_bandwidth_table.clone())
+                .unwrap_or_default();
+            connection_data.upload_bandwidth_table = prev_data
+                .map(|data| data.upload_bandwidth_table.clone())
+                .unwrap_or_default();
+            record_bandwidth_sample(
+                &mut connection_data.download_bandwidth_table,
+                connection_data.total_bytes_downloaded,
+            );
+            record_bandwidth_sample(
+                &mut connection_data.upload_bandwidth_table,
+                connection_data.total_bytes_uploaded,
+            );
+        }
+
         UIState {
             processes,
             remote_addresses,
@@ -146,3 +319,133 @@ impl UIState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bandwidth_table_tracks_the_peak_sample_and_evicts_the_oldest() {
+        let mut table = Vec::new();
+        for sample in [100, 500, 200] {
+            record_bandwidth_sample(&mut table, sample);
+        }
+        assert_eq!(calc_max_bandwidth(&table), 500);
+
+        // Push enough new samples to fully evict the current window,
+        // including the peak recorded above.
+        for sample in 0..BANDWIDTH_TABLE_LENGTH {
+            record_bandwidth_sample(&mut table, sample as u128);
+        }
+        assert_eq!(table.len(), BANDWIDTH_TABLE_LENGTH);
+        assert_eq!(
+            calc_max_bandwidth(&table),
+            (BANDWIDTH_TABLE_LENGTH - 1) as u128
+        );
+    }
+
+    #[test]
+    fn processes_only_refresh_kind_still_decays_the_process_average() {
+        use crate::network::{ConnectionInfo, Protocol};
+
+        let connection = Connection::new(
+            Protocol::Tcp,
+            "127.0.0.1".parse().unwrap(),
+            56789,
+            "10.0.0.1".parse().unwrap(),
+            443,
+        )
+        .unwrap();
+
+        let mut connections_to_procs = HashMap::new();
+        connections_to_procs.insert(connection, "proc".to_string());
+
+        // Only the processes view is enabled, so `old_state.connections`
+        // stays empty across refreshes.
+        let refresh_kind = RefreshKind::new().with_processes();
+
+        let mut utilization = Utilization::default();
+        utilization.connections.insert(
+            connection,
+            ConnectionInfo {
+                total_bytes_downloaded: 1000,
+                total_bytes_uploaded: 0,
+                interface_name: "eth0".to_string(),
+            },
+        );
+        let round_one = UIState::new(
+            connections_to_procs.clone(),
+            utilization,
+            &UIState::default(),
+            refresh_kind,
+        );
+        assert_eq!(
+            round_one
+                .processes
+                .get("proc")
+                .unwrap()
+                .get_avg_bytes_downloaded(),
+            1000
+        );
+
+        let mut utilization = Utilization::default();
+        utilization.connections.insert(
+            connection,
+            ConnectionInfo {
+                total_bytes_downloaded: 2000,
+                total_bytes_uploaded: 0,
+                interface_name: "eth0".to_string(),
+            },
+        );
+        let round_two = UIState::new(connections_to_procs, utilization, &round_one, refresh_kind);
+
+        // Without seeding the process average from `old_state.processes`,
+        // this would collapse to the raw 2000 instead of decaying against
+        // round one's average.
+        assert_eq!(
+            round_two
+                .processes
+                .get("proc")
+                .unwrap()
+                .get_avg_bytes_downloaded(),
+            1500
+        );
+    }
+
+    #[test]
+    fn ipv6_remote_addresses_are_counted_instead_of_dropped() {
+        use crate::network::{ConnectionInfo, Protocol};
+
+        let remote_ip: IpAddr = "2001:db8::1".parse().unwrap();
+        let connection =
+            Connection::new(Protocol::Tcp, "::1".parse().unwrap(), 56789, remote_ip, 443).unwrap();
+
+        let mut connections_to_procs = HashMap::new();
+        connections_to_procs.insert(connection, "proc".to_string());
+
+        let mut utilization = Utilization::default();
+        utilization.connections.insert(
+            connection,
+            ConnectionInfo {
+                total_bytes_downloaded: 1000,
+                total_bytes_uploaded: 500,
+                interface_name: "eth0".to_string(),
+            },
+        );
+
+        let state = UIState::new(
+            connections_to_procs,
+            utilization,
+            &UIState::default(),
+            RefreshKind::everything(),
+        );
+
+        let data_for_remote_address = state
+            .remote_addresses
+            .get(&remote_ip)
+            .expect("the IPv6 remote address should be tracked, not dropped");
+        assert_eq!(data_for_remote_address.total_bytes_downloaded, 1000);
+        assert_eq!(data_for_remote_address.total_bytes_uploaded, 500);
+        assert_eq!(state.total_bytes_downloaded, 1000);
+    }
+}
@@ -0,0 +1,55 @@
+use ::std::collections::HashMap;
+use ::std::net::IpAddr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Socket {
+    pub ip: IpAddr,
+    pub port: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Connection {
+    pub local_socket: Socket,
+    pub remote_socket: Socket,
+    pub protocol: Protocol,
+}
+
+impl Connection {
+    pub fn new(
+        protocol: Protocol,
+        local_ip: IpAddr,
+        local_port: u16,
+        remote_ip: IpAddr,
+        remote_port: u16,
+    ) -> Option<Self> {
+        Some(Connection {
+            local_socket: Socket {
+                ip: local_ip,
+                port: local_port,
+            },
+            remote_socket: Socket {
+                ip: remote_ip,
+                port: remote_port,
+            },
+            protocol,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionInfo {
+    pub total_bytes_downloaded: u128,
+    pub total_bytes_uploaded: u128,
+    pub interface_name: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Utilization {
+    pub connections: HashMap<Connection, ConnectionInfo>,
+}